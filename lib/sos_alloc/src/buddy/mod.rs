@@ -5,6 +5,14 @@ use self::math::PowersOf2Ext;
 
 use core::mem;
 use core::cmp::{max, min};
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::null_mut;
+
+extern crate spin;
+use self::spin::Mutex;
+
+extern crate params;
+use self::params::InitParams;
 
 pub struct Free { next: RawLink<Free> }
 
@@ -25,7 +33,7 @@ pub struct FreeList<'a> {
 impl<'a> FreeList<'a> {
 
     /// Create a new empty `FreeList`
-    const fn new() -> FreeList<'a> {
+    pub(crate) const fn new() -> FreeList<'a> {
         FreeList { head: None, length: 0 }
     }
 
@@ -34,7 +42,7 @@ impl<'a> FreeList<'a> {
     /// # Unsafe due to
     ///   - `mem::transmute()`
     ///   - Dereferencing a raw pointer
-    unsafe fn push(&mut self, block: *mut u8) {
+    pub(crate) unsafe fn push(&mut self, block: *mut u8) {
         let block_ptr = block as *mut Free;
         // be nice if rawlink was kinder to pattern-matching but whatever
         *block_ptr = if let Some(head) = self.head.take() {
@@ -54,13 +62,17 @@ impl<'a> FreeList<'a> {
     /// # Unsafe due to
     ///   - `mem::transmute()`
     ///   - Dereferencing a raw pointer
-    unsafe fn pop(&mut self) -> Option<*mut u8> {
+    pub(crate) unsafe fn pop(&mut self) -> Option<*mut u8> {
         self.head.take()
             .map(|head| {
-                let popped_block
-                    = mem::replace(&mut self.head, head.next.resolve_mut());
-                let block_ptr: *mut u8
-                    = mem::transmute(popped_block);
+                // `head` (not the new `self.head` we're about to install) is
+                // the node we're popping; the old code transmuted the
+                // *replaced* value of `self.head`, which was already `None`
+                // thanks to the `.take()` above, and so always handed back
+                // a null pointer.
+                self.head = head.next.resolve_mut();
+                self.length -= 1;
+                let block_ptr: *mut u8 = mem::transmute(head);
                 block_ptr
             })
     }
@@ -81,14 +93,43 @@ impl<'a> FreeList<'a> {
     ///   - `false` if the block was not present in the free list
     unsafe fn remove(&mut self, target_block: *mut u8) -> bool {
         let target_ptr = target_block as *mut Free;
-        for block in self.iter_mut() {
-            let block_ptr: *mut Free = block;
-            if block_ptr == target_ptr {
-                *block_ptr = Free { next: block.next.take() };
-                return true;
+
+        // the head has no predecessor to repoint, so it's handled
+        // separately from the rest of the list.
+        let is_head = match self.head {
+            Some(ref head) => (head as *const Free as *mut Free) == target_ptr
+          , None           => false
+        };
+        if is_head {
+            let head = self.head.take().unwrap();
+            self.head = head.next.resolve_mut();
+            self.length -= 1;
+            return true;
+        }
+
+        // walk the list one node behind the search, so that when we find
+        // the target we can splice it out by repointing its predecessor's
+        // `next` at the target's own `next` (the old code instead just
+        // reassigned the target's `next` to itself, a no-op that left it
+        // physically linked into the list).
+        let mut prev: *mut Free = match self.head {
+            Some(ref mut head) => *head as *mut Free
+          , None               => return false
+        };
+        loop {
+            match (*prev).next.resolve_mut() {
+                Some(next) => {
+                    let next_ptr: *mut Free = next;
+                    if next_ptr == target_ptr {
+                        (*prev).next = (*next_ptr).next.take();
+                        self.length -= 1;
+                        return true;
+                    }
+                    prev = next_ptr;
+                }
+              , None => return false
             }
         }
-        false
     }
 
     /// Returns an iterator over the blocks in this free list
@@ -151,6 +192,13 @@ pub struct BuddyHeapAllocator<'a> {
     start_addr: *mut u8
   , /// The allocator's free list
     free_lists: &'a mut [FreeList<'a>]
+  , /// Bitmap tracking, for each order, whether the block-sized slot at a
+    /// given index is currently free.
+    ///
+    /// This lets us test whether a block's buddy is free in O(1) time when
+    /// coalescing, rather than relying on `FreeList::remove()`'s linear
+    /// scan to discover whether the buddy is present.
+    bitmap: &'a mut [u8]
   , /// Number of blocks in the heap (must be a power of 2)
     heap_size: usize
   , /// Minimum block size
@@ -160,6 +208,7 @@ pub struct BuddyHeapAllocator<'a> {
 impl<'a> BuddyHeapAllocator<'a> {
     pub unsafe fn new( start_addr: *mut u8
                      , free_lists: &'a mut [FreeList<'a>]
+                     , bitmap: &'a mut [u8]
                      , heap_size: usize) -> BuddyHeapAllocator<'a> {
         let n_free_lists = free_lists.len();
 
@@ -181,13 +230,132 @@ impl<'a> BuddyHeapAllocator<'a> {
         let mut heap
             = BuddyHeapAllocator { start_addr: start_addr
                                  , free_lists: free_lists
+                                 , bitmap: bitmap
                                  , heap_size: heap_size
                                  , min_block_size: min_block_size
                                  };
+
+        assert!( heap.bitmap.len() * 8 >= heap.n_bitmap_bits()
+               , "Bitmap is too small to track every block in the heap.");
+
         // TODO: put first head block on appropriately-sized freelist
         heap
     }
 
+    /// Construct a new `BuddyHeapAllocator` seeded from the boot-time
+    /// memory layout described by `params`.
+    ///
+    /// The usable heap span is taken from `params.heap_base` and
+    /// `params.heap_top`, and is greedily carved into the largest aligned
+    /// power-of-two blocks that fit, which are pushed onto the free list
+    /// for their order. Any sub-range that overlaps a reserved or unusable
+    /// `mem::Area` in `params`' memory map is skipped, rather than freed.
+    pub unsafe fn from_init_params( params: &InitParams
+                                   , free_lists: &'a mut [FreeList<'a>]
+                                   , bitmap: &'a mut [u8]
+                                   ) -> BuddyHeapAllocator<'a> {
+        let heap_base = usize::from(params.heap_base);
+        let heap_top = usize::from(params.heap_top);
+
+        let mut heap = Self::new( heap_base as *mut u8
+                                 , free_lists
+                                 , bitmap
+                                 , heap_top - heap_base );
+
+        let max_order = heap.free_lists.len() - 1;
+        let max_block_size = heap.min_block_size << max_order;
+
+        let mut addr = heap_base;
+        while addr + heap.min_block_size <= heap_top {
+            // the block's alignment (and thus its maximum possible size)
+            // must be computed relative to `heap_base`, not as an absolute
+            // address: `bit_index()`/`buddy_of()` both work in terms of
+            // `block - start_addr`, and `start_addr` (== `heap_base`) is
+            // only guaranteed to be aligned on a `PAGE_SIZE` boundary, not
+            // on the boundary of every larger order.
+            let block_size = carve_block_size( addr - heap_base
+                                              , heap_top - addr
+                                              , heap.min_block_size
+                                              , max_block_size );
+
+            if heap.area_is_reserved(params, addr, block_size) {
+                // this block overlaps a reserved area; don't free it, and
+                // try again one minimum block further along.
+                addr += heap.min_block_size;
+                continue;
+            }
+
+            let order = block_size.log2() - heap.min_block_size.log2();
+            heap.set_free(addr as *mut u8, order, true);
+            heap.free_lists[order].push(addr as *mut u8);
+            addr += block_size;
+        }
+
+        heap
+    }
+
+    /// Returns whether the `size`-byte range of memory starting at `addr`
+    /// overlaps a reserved or unusable area of `params`' memory map.
+    fn area_is_reserved( &self
+                       , params: &InitParams
+                       , addr: usize
+                       , size: usize) -> bool {
+        let end = addr + size;
+        params.mem_map()
+            .any(|area| !area.is_usable()
+                      && usize::from(area.start()) < end
+                      && usize::from(area.end()) > addr)
+    }
+
+    /// Returns the number of blocks of the given `order` in the heap.
+    #[inline]
+    fn n_blocks(&self, order: usize) -> usize {
+        self.heap_size / (self.min_block_size << order)
+    }
+
+    /// Returns the total number of bits needed to track every block at
+    /// every order in the heap.
+    #[inline]
+    fn n_bitmap_bits(&self) -> usize {
+        (0..self.free_lists.len()).map(|o| self.n_blocks(o)).sum()
+    }
+
+    /// Returns the index of the first bit belonging to the given `order`.
+    #[inline]
+    fn bitmap_base(&self, order: usize) -> usize {
+        (0..order).map(|o| self.n_blocks(o)).sum()
+    }
+
+    /// Returns the index, within the bitmap, of the bit tracking the block
+    /// at `block` of the given `order`.
+    #[inline]
+    fn bit_index(&self, block: *mut u8, order: usize) -> usize {
+        let block_index
+            = (block as usize - self.start_addr as usize)
+                / (self.min_block_size << order);
+        self.bitmap_base(order) + block_index
+    }
+
+    /// Returns whether the block at `block` of the given `order` is marked
+    /// free in the bitmap.
+    #[inline]
+    fn is_free(&self, block: *mut u8, order: usize) -> bool {
+        let idx = self.bit_index(block, order);
+        self.bitmap[idx / 8] & (1 << (idx % 8)) != 0
+    }
+
+    /// Sets whether the block at `block` of the given `order` is marked
+    /// free in the bitmap.
+    #[inline]
+    fn set_free(&mut self, block: *mut u8, order: usize, free: bool) {
+        let idx = self.bit_index(block, order);
+        if free {
+            self.bitmap[idx / 8] |= 1 << (idx % 8);
+        } else {
+            self.bitmap[idx / 8] &= !(1 << (idx % 8));
+        }
+    }
+
     /// Computes the size of an allocation request.
     ///
     /// # Arguments
@@ -199,25 +367,32 @@ impl<'a> BuddyHeapAllocator<'a> {
     ///   - `Some(usize)` containing the size needed if the request is valid
     #[inline]
     pub fn alloc_size(&self, size: usize, align: usize) -> Option<usize> {
-        // Pre-check if this is a valid allocation request:
-        //  - allocations must be aligned on power of 2 boundaries
-        //  - we cannot allocate requests with alignments greater than the
-        //    base alignment of the heap without jumping through a bunch of
-        //    hoops.
-        if !align.is_pow2() || align > ::PAGE_SIZE {
+        // Pre-check if this is a valid allocation request: allocations
+        // must be aligned on power of 2 boundaries.
+        if !align.is_pow2() {
             None
         // If the request is valid, compute the size we need to allocate
         } else {
-            let alloc_size
+            let alloc_size = if align > ::PAGE_SIZE {
+                // we cannot guarantee a block aligned on more than
+                // `PAGE_SIZE`, since the heap base is only guaranteed to be
+                // `PAGE_SIZE`-aligned. Instead, over-allocate a block large
+                // enough that an `align`-aligned address can always be
+                // found somewhere inside it, after also setting aside room
+                // for the header `allocate_aligned()` uses to recover the
+                // block's true base.
+                (size + align + mem::size_of::<Free>()).next_pow2()
+            } else {
                 // the allocation size for the request is the next power of 2
                 // after the size of the request, the alignment of the request,
                 // or the minimum block size (whichever is greatest).
-                = max!( size
+                max!( size
                         // we can't allocate less than the minimum block size
-                      , self.min_block_size
+                    , self.min_block_size
                         // we can't allocate less than the alignment, either
-                      , align )
-                    .next_pow2();
+                    , align )
+                    .next_pow2()
+            };
 
             if alloc_size > self.heap_size {
                 // if the calculated size is greater than the size of the heap,
@@ -245,5 +420,403 @@ impl<'a> BuddyHeapAllocator<'a> {
             )
     }
 
-    // pub unsafe fn allocate
+    /// Allocate a block of the given `order`.
+    ///
+    /// This finds the smallest non-empty free list of order `j >= order`,
+    /// pops a block off of it, and splits that block down to the requested
+    /// order, pushing the unused halves onto the free lists for the orders
+    /// in between.
+    ///
+    /// # Returns
+    ///   - `Some(*mut u8)` pointing to the base of the allocated block
+    ///   - `None` if no free block large enough exists anywhere in the heap
+    ///
+    /// # Unsafe due to
+    ///   - Calling `FreeList::pop()` and `FreeList::push()`
+    pub unsafe fn allocate(&mut self, order: usize) -> Option<*mut u8> {
+        // scan upward for the smallest order with a free block available
+        let mut j = order;
+        while j < self.free_lists.len() && self.free_lists[j].length == 0 {
+            j += 1;
+        }
+
+        if j == self.free_lists.len() {
+            // no free list of a large enough order had any blocks left
+            return None;
+        }
+
+        let block = self.free_lists[j].pop()
+            .expect("free list length was nonzero, but pop() returned None!");
+        self.set_free(block, j, false);
+
+        // split the block down to the requested order, pushing the unused
+        // upper half of each split onto the free list for that order
+        while j > order {
+            let upper_half
+                = block.offset((self.min_block_size << (j - 1)) as isize);
+            self.set_free(upper_half, j - 1, true);
+            self.free_lists[j - 1].push(upper_half);
+            j -= 1;
+        }
+
+        Some(block)
+    }
+
+    /// Deallocate a block of the given `order`, returning it to the heap.
+    ///
+    /// If the block's buddy is also free, the two blocks are merged into a
+    /// block of order `order + 1`, and the merge is repeated upward until
+    /// the buddy at some order is not free, or the maximum order is
+    /// reached.
+    ///
+    /// # Unsafe due to
+    ///   - Calling `FreeList::remove()` and `FreeList::push()`
+    ///   - Dereferencing `block`, which must actually point to a block of
+    ///     the given `order` previously returned by `allocate()`
+    pub unsafe fn deallocate(&mut self, block: *mut u8, order: usize) {
+        let mut block = block;
+        let mut order = order;
+
+        while order < self.free_lists.len() - 1 {
+            let buddy = self.buddy_of(block, order);
+            // test the buddy's bit directly, rather than linearly scanning
+            // the free list with `remove()` to discover whether it's there.
+            if self.is_free(buddy, order) {
+                // the buddy is free; remove it from its free list and
+                // merge the two blocks, then keep trying to coalesce
+                // upward.
+                self.set_free(buddy, order, false);
+                let removed = self.free_lists[order].remove(buddy);
+                debug_assert!( removed
+                             , "bitmap said buddy was free, but it was not \
+                                found in the free list!");
+                block = min(block, buddy);
+                order += 1;
+            } else {
+                // the buddy is not free; we can't coalesce any further.
+                break;
+            }
+        }
+
+        self.set_free(block, order, true);
+        self.free_lists[order].push(block);
+    }
+
+    /// Computes the address of the buddy of the block at `block`, which is
+    /// of the given `order`.
+    #[inline]
+    fn buddy_of(&self, block: *mut u8, order: usize) -> *mut u8 {
+        let offset = block as usize - self.start_addr as usize;
+        let buddy_offset = offset ^ (self.min_block_size << order);
+        unsafe { self.start_addr.offset(buddy_offset as isize) }
+    }
+
+    /// Returns the minimum block size this allocator can hand out.
+    #[inline]
+    pub(crate) fn min_block_size(&self) -> usize {
+        self.min_block_size
+    }
+
+    /// Marks `block` free in the bitmap and pushes it onto the free list
+    /// for `order`, without going through `from_init_params()`.
+    ///
+    /// This only exists to let tests (in this crate and in `slab`) seed a
+    /// heap with a known free block, since `new()` does not populate any
+    /// free list on its own.
+    #[cfg(test)]
+    pub(crate) unsafe fn seed_free_block(&mut self, block: *mut u8, order: usize) {
+        self.set_free(block, order, true);
+        self.free_lists[order].push(block);
+    }
+
+    /// Allocate `size` bytes aligned to `align`.
+    ///
+    /// For `align <= PAGE_SIZE`, this is equivalent to
+    /// `allocate(self.alloc_order(size, align)?)`, since every block
+    /// handed out by the buddy allocator lies at an offset from the
+    /// `PAGE_SIZE`-aligned heap base that is a multiple of its own size,
+    /// and `alloc_size()` already grows the allocation to be at least
+    /// `align`.
+    ///
+    /// For `align > PAGE_SIZE`, the block is over-allocated (see
+    /// `alloc_size()`) with room set aside up front for a header, so that
+    /// an `align`-aligned address can always be found at or after
+    /// `block + mem::size_of::<Free>()`. The true base of the block is
+    /// stashed in the `mem::size_of::<usize>()` bytes immediately before
+    /// the returned pointer, so that `deallocate_aligned()` can recover
+    /// it; reserving the header space up front (rather than searching for
+    /// alignment from `block` itself and hoping enough padding happens to
+    /// land before it) guarantees there's always room for it.
+    ///
+    /// # Unsafe due to
+    ///   - Calling `allocate()`
+    ///   - Dereferencing the computed pointer to stash the block's true base
+    pub unsafe fn allocate_aligned(&mut self, size: usize, align: usize) -> Option<*mut u8> {
+        let order = self.alloc_order(size, align)?;
+        let block = self.allocate(order)?;
+
+        if align <= ::PAGE_SIZE {
+            return Some(block);
+        }
+
+        let header_end = block as usize + mem::size_of::<Free>();
+        let aligned = align_up(header_end, align) as *mut u8;
+
+        *(aligned as *mut usize).offset(-1) = block as usize;
+
+        Some(aligned)
+    }
+
+    /// Deallocate a block previously returned by `allocate_aligned()` with
+    /// the same `size` and `align`.
+    ///
+    /// # Unsafe due to
+    ///   - Dereferencing `ptr` to recover the block's true base, if needed
+    ///   - Calling `deallocate()`
+    pub unsafe fn deallocate_aligned(&mut self, ptr: *mut u8, size: usize, align: usize) {
+        let order = self.alloc_order(size, align)
+            .expect("deallocate_aligned() called with an invalid size/align!");
+
+        let block = if align <= ::PAGE_SIZE {
+            ptr
+        } else {
+            *(ptr as *mut usize).offset(-1) as *mut u8
+        };
+
+        self.deallocate(block, order);
+    }
+}
+
+/// Rounds `addr` up to the next multiple of `align`.
+///
+/// `align` must be a power of two.
+#[inline]
+fn align_up(addr: usize, align: usize) -> usize {
+    (addr + align - 1) & !(align - 1)
+}
+
+/// Computes the size of the largest power-of-two block that can be carved
+/// out at `offset` bytes into a heap, given `remaining` bytes left before
+/// the end of the heap.
+///
+/// `offset` must be relative to the heap's base address, rather than an
+/// absolute address, since the returned size must be a divisor of
+/// `offset` in order for the carved block to land on a valid boundary
+/// for its order (the heap base is only guaranteed to be aligned on a
+/// `PAGE_SIZE` boundary, not on the boundary of every larger order).
+#[inline]
+fn carve_block_size( offset: usize
+                    , remaining: usize
+                    , min_block_size: usize
+                    , max_block_size: usize) -> usize {
+    let align_size = if offset == 0 {
+        max_block_size
+    } else {
+        1usize << offset.trailing_zeros()
+    };
+    let mut block_size = min(align_size, max_block_size);
+    while block_size > min_block_size && block_size > remaining {
+        block_size >>= 1;
+    }
+    block_size
+}
+
+/// A wrapper providing interior mutability around an allocator.
+///
+/// `BuddyHeapAllocator`'s `allocate`/`deallocate` methods require `&mut
+/// self`, but the `GlobalAlloc` trait only hands us `&self`. `Locked` wraps
+/// an allocator in a spinlock so that it can be used as a
+/// `#[global_allocator]`.
+pub struct Locked<A> {
+    inner: Mutex<A>
+}
+
+impl<A> Locked<A> {
+    /// Construct a new `Locked` wrapping `inner`.
+    pub const fn new(inner: A) -> Locked<A> {
+        Locked { inner: Mutex::new(inner) }
+    }
+
+    /// Lock the inner allocator, blocking until it becomes available.
+    pub(crate) fn lock(&self) -> spin::MutexGuard<A> {
+        self.inner.lock()
+    }
+}
+
+unsafe impl<'a> GlobalAlloc for Locked<BuddyHeapAllocator<'a>> {
+
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.lock()
+            .allocate_aligned(layout.size(), layout.align())
+            .unwrap_or(null_mut())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.lock().deallocate_aligned(ptr, layout.size(), layout.align());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{alloc_zeroed, Layout};
+    use std::slice;
+
+    /// Builds the raw buffers a `BuddyHeapAllocator` borrows from: a
+    /// `::PAGE_SIZE`-aligned heap buffer, one `FreeList` per order, and an
+    /// (oversized, for simplicity) bitmap, so individual tests don't each
+    /// have to hand-declare their own `#[repr(align(PAGE_SIZE))]` fixture.
+    ///
+    /// The heap buffer is leaked rather than freed: tests are short-lived
+    /// processes, and the allocator borrows from these buffers for the rest
+    /// of the test.
+    fn test_heap(heap_size: usize, n_orders: usize)
+        -> (&'static mut [u8], Vec<FreeList<'static>>, Vec<u8>)
+    {
+        let layout = Layout::from_size_align(heap_size, ::PAGE_SIZE)
+            .expect("invalid test heap size/alignment");
+        let heap_mem = unsafe {
+            let ptr = alloc_zeroed(layout);
+            assert!(!ptr.is_null(), "test heap allocation failed");
+            slice::from_raw_parts_mut(ptr, heap_size)
+        };
+
+        let free_lists = (0..n_orders).map(|_| FreeList::new()).collect();
+        // every order's bits fit in well under one byte per heap byte
+        let bitmap = vec![0u8; heap_size];
+
+        (heap_mem, free_lists, bitmap)
+    }
+
+    #[test]
+    fn carve_block_size_uses_heap_relative_alignment() {
+        // `heap_base = 0x11000`: page-aligned (on a 4k boundary), but not
+        // aligned on the 8k/16k boundaries of the larger orders.
+        let min_block_size = 4096;
+        let max_block_size = 16384;
+
+        // the first block carved is free to be the largest size, since no
+        // part of the heap has been carved out yet.
+        assert_eq!( carve_block_size(0, 16384, min_block_size, max_block_size)
+                  , 16384 );
+
+        // after carving a 4096-byte block, the next block starts at a
+        // *relative* offset of 4096 bytes into the heap. Even though the
+        // corresponding absolute address (0x11000 + 0x1000 = 0x12000)
+        // happens to be 8k-aligned, the carved block must stay order-0
+        // sized (4096 bytes), since only 4096 bytes of this heap have
+        // been carved out so far, and an 8k block here would not land on
+        // a valid order-1 boundary relative to `heap_base`.
+        assert_eq!( carve_block_size(4096, 16384 - 4096, min_block_size, max_block_size)
+                  , 4096 );
+    }
+
+    #[test]
+    fn allocate_aligned_never_panics_on_tight_padding() {
+        const HEAP_SIZE: usize = 32768;
+
+        let (heap_mem, mut free_lists, mut bitmap) = test_heap(HEAP_SIZE, 2);
+        let heap_base = heap_mem.as_mut_ptr();
+
+        let mut heap = unsafe {
+            BuddyHeapAllocator::new( heap_base
+                                   , &mut free_lists
+                                   , &mut bitmap
+                                   , HEAP_SIZE )
+        };
+        unsafe { heap.seed_free_block(heap_base, 0); }
+
+        // `align` is greater than `::PAGE_SIZE`, so this takes the
+        // over-allocation path. Regardless of where the underlying block
+        // happens to land relative to `align`, this must succeed rather
+        // than panicking on insufficient padding.
+        let align = ::PAGE_SIZE * 2;
+        let ptr = unsafe { heap.allocate_aligned(100, align) }
+            .expect("over-aligned allocation should succeed");
+        assert_eq!( ptr as usize % align, 0
+                  , "returned pointer must be aligned to `align`");
+
+        unsafe { heap.deallocate_aligned(ptr, 100, align); }
+    }
+
+    #[test]
+    fn allocate_splits_and_deallocate_merges() {
+        const HEAP_SIZE: usize = 1024;
+
+        let (heap_mem, mut free_lists, mut bitmap) = test_heap(HEAP_SIZE, 3);
+        let heap_base = heap_mem.as_mut_ptr();
+
+        let mut heap = unsafe {
+            BuddyHeapAllocator::new( heap_base
+                                   , &mut free_lists
+                                   , &mut bitmap
+                                   , HEAP_SIZE )
+        };
+        // seed the whole heap as a single order-2 (1024-byte) block, since
+        // `new()` does not populate any free list on its own.
+        unsafe { heap.seed_free_block(heap_base, 2); }
+
+        // allocating an order-0 (256-byte) block should split the
+        // order-2 block all the way down, leaving the unused upper half
+        // of each split on the free list for that order.
+        let a = unsafe { heap.allocate(0) }.expect("first allocation should succeed");
+        assert_eq!(heap.free_lists[2].length, 0);
+        assert_eq!(heap.free_lists[1].length, 1);
+        assert_eq!(heap.free_lists[0].length, 1);
+
+        // the second order-0 allocation should be served from the split
+        // leftover, without needing to split anything further.
+        let b = unsafe { heap.allocate(0) }.expect("second allocation should succeed");
+        assert_ne!(a, b, "two live allocations must not alias");
+        assert_eq!(heap.free_lists[1].length, 1);
+        assert_eq!(heap.free_lists[0].length, 0);
+
+        // freeing both blocks should coalesce them back up through every
+        // order, all the way to a single order-2 block.
+        unsafe {
+            heap.deallocate(a, 0);
+            heap.deallocate(b, 0);
+        }
+        assert_eq!(heap.free_lists[2].length, 1);
+        assert_eq!(heap.free_lists[1].length, 0);
+        assert_eq!(heap.free_lists[0].length, 0);
+    }
+
+    #[test]
+    fn bitmap_tracks_free_state_independently_per_block_and_order() {
+        const HEAP_SIZE: usize = 1024;
+
+        let (heap_mem, mut free_lists, mut bitmap) = test_heap(HEAP_SIZE, 3);
+        let block1 = heap_mem.as_mut_ptr();
+
+        let mut heap = unsafe {
+            BuddyHeapAllocator::new( block1
+                                   , &mut free_lists
+                                   , &mut bitmap
+                                   , HEAP_SIZE )
+        };
+
+        let block2 = unsafe { block1.offset(heap.min_block_size as isize) };
+
+        // every block starts out marked non-free
+        assert!(!heap.is_free(block1, 0));
+        assert!(!heap.is_free(block1, 1));
+
+        heap.set_free(block1, 0, true);
+        assert!(heap.is_free(block1, 0));
+        // setting the order-0 bit for `block1` must not affect the
+        // order-1 bit at the same address, since `bitmap_base()` gives
+        // each order a disjoint region of the bitmap.
+        assert!( !heap.is_free(block1, 1)
+               , "order-1 bit must be independent of order-0 bit for the \
+                  same address");
+        // nor should it affect the order-0 bit of a different block.
+        assert!( !heap.is_free(block2, 0)
+               , "adjacent blocks at the same order must have \
+                  independent bits");
+
+        heap.set_free(block1, 0, false);
+        assert!(!heap.is_free(block1, 0));
+    }
 }